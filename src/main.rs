@@ -1,12 +1,45 @@
-use clap::Parser;
+use chrono::{Local, SecondsFormat};
+use clap::{Parser, ValueEnum};
+use crossbeam_utils::CachePadded;
+use rand::seq::SliceRandom;
 use rand::Rng;
+use std::io::{BufRead, BufReader, Write};
 use std::net::IpAddr;
-use std::net::UdpSocket;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Transport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Framing {
+    OctetCounting,
+    Lf,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Format {
+    Raw,
+    Rfc3164,
+    Rfc5424,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Report {
+    Line,
+    Json,
+    Histogram,
+}
+
 #[derive(Parser, Debug)]
 #[command(
     name = "syslog-hv",
@@ -28,6 +61,40 @@ struct Args {
         default_value_t = 514
     )]
     target_port: u16,
+    #[arg(
+        short = 'T',
+        long = "transport",
+        value_name = "transport to use",
+        default_value = "udp"
+    )]
+    transport: Transport,
+    #[arg(
+        short = 'f',
+        long = "framing",
+        value_name = "stream framing (RFC 6587) for tcp/tls",
+        default_value = "octet-counting"
+    )]
+    framing: Framing,
+    #[arg(
+        long = "tls-insecure",
+        help = "skip TLS certificate and hostname verification",
+        default_value_t = false
+    )]
+    tls_insecure: bool,
+    #[arg(
+        short = 'm',
+        long = "format",
+        value_name = "syslog wire format",
+        default_value = "raw"
+    )]
+    format: Format,
+    #[arg(
+        short = 'c',
+        long = "facility",
+        value_name = "syslog facility (combined with severity into PRI)",
+        default_value_t = 1
+    )]
+    facility: u8,
     #[arg(
         short = 't',
         long = "threads",
@@ -63,6 +130,37 @@ struct Args {
         default_value_t = 0
     )]
     words_fixed: u8,
+    #[arg(
+        short = 'b',
+        long = "batch",
+        value_name = "messages to flush per batch (sendmmsg on udp, sequential writes on tcp/tls)",
+        default_value_t = 1
+    )]
+    batch: usize,
+    #[arg(
+        long = "target-pps",
+        value_name = "aggregate packets/sec to pace to (0 = unpaced)",
+        default_value_t = 0.0
+    )]
+    target_pps: f64,
+    #[arg(
+        long = "target-mbit",
+        value_name = "aggregate Mbit/s to pace to (0 = unpaced)",
+        default_value_t = 0.0
+    )]
+    target_mbit: f64,
+    #[arg(
+        long = "control-addr",
+        value_name = "TCP address for the live control/stats socket"
+    )]
+    control_addr: Option<String>,
+    #[arg(
+        short = 'r',
+        long = "report",
+        value_name = "stats output mode",
+        default_value = "line"
+    )]
+    report: Report,
 }
 
 #[derive(Debug)]
@@ -93,81 +191,656 @@ impl Target {
     }
 }
 
+// The knobs that can be retuned at runtime over the control socket live behind
+// atomics so `tx_thread` can read them each iteration without a lock; the rest
+// are fixed at launch.
 #[derive(Debug)]
 struct Options {
-    sleep_us: u64,
-    priority_max: u8,
-    words_max: u8,
-    words_fixed: u8,
+    sleep_us: AtomicU64,
+    priority_max: AtomicU64,
+    words_max: AtomicU64,
+    words_fixed: AtomicU64,
+    paused: AtomicBool,
+    facility: u8,
+    batch: usize,
+    target_pps: f64,
+    target_mbit: f64,
 }
 
-#[derive(Debug)]
-struct Stats {
-    packets: u64,
-    bytes: u64,
+// Number of power-of-two buckets for inter-send intervals, covering roughly
+// 1 us .. 2^31 us (~35 min), which is far wider than any realistic interval.
+const HIST_BUCKETS: usize = 32;
+
+// Lock-free per-thread counters. Each tx thread owns one, cache-padded so the
+// threads never share a cache line, and the reporter sums them once a second.
+struct ThreadStats {
+    packets: AtomicU64,
+    bytes: AtomicU64,
+    buckets: [AtomicU64; HIST_BUCKETS],
+}
+
+impl ThreadStats {
+    fn new() -> Self {
+        ThreadStats {
+            packets: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    // Record an inter-send interval into its power-of-two bucket.
+    fn record_interval(&self, us: u64) {
+        let idx = bucket_index(us);
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Shared handle to every tx thread's counters.
+type AllStats = Arc<Vec<Arc<CachePadded<ThreadStats>>>>;
+
+// floor(log2(us)), clamped to the bucket count; 0 us falls in bucket 0.
+fn bucket_index(us: u64) -> usize {
+    if us == 0 {
+        0
+    } else {
+        ((63 - us.leading_zeros()) as usize).min(HIST_BUCKETS - 1)
+    }
+}
+
+// Lower bound in microseconds of histogram bucket `i` (2^i us; bucket 0 is 0).
+fn bucket_floor_us(i: usize) -> u64 {
+    if i == 0 {
+        0
+    } else {
+        1u64 << i
+    }
+}
+
+// Return the (min, p50, p99) send interval in microseconds from a bucket-count
+// distribution, reported as the floor of the bucket each percentile lands in.
+fn percentiles(buckets: &[u64; HIST_BUCKETS]) -> (u64, u64, u64) {
+    let total: u64 = buckets.iter().sum();
+    if total == 0 {
+        return (0, 0, 0);
+    }
+
+    let at = |pct: f64| -> u64 {
+        let threshold = (total as f64 * pct).ceil() as u64;
+        let mut cumulative = 0u64;
+        for (i, &count) in buckets.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= threshold {
+                return bucket_floor_us(i);
+            }
+        }
+        bucket_floor_us(HIST_BUCKETS - 1)
+    };
+
+    let min = buckets
+        .iter()
+        .position(|&c| c > 0)
+        .map(bucket_floor_us)
+        .unwrap_or(0);
+
+    (min, at(0.50), at(0.99))
 }
 
 struct Config {
     target: Target,
+    transport: Transport,
+    framing: Framing,
+    format: Format,
+    report: Report,
+    tls_insecure: bool,
+    threads_nr: usize,
     options: Options,
 }
 
-fn tx_thread(signalled: Arc<AtomicBool>, config: Arc<Config>, stats: Arc<Mutex<Stats>>) {
-    let socket = UdpSocket::bind(config.target.bind_to()).unwrap();
+// A simple token bucket: `tokens` accrue at `rate` per second up to `burst`,
+// and each send spends `cost` tokens, sleeping when the bucket runs dry. Used
+// to pace either packets/sec (cost = 1 per datagram) or bits/sec (cost = bytes).
+struct RateLimiter {
+    rate: f64,
+    tokens: f64,
+    burst: f64,
+    last: Instant,
+}
 
-    let mut rng = rand::thread_rng();
+impl RateLimiter {
+    fn new(rate: f64) -> Self {
+        RateLimiter {
+            rate,
+            tokens: 0.0,
+            burst: rate.max(1.0),
+            last: Instant::now(),
+        }
+    }
+
+    fn acquire(&mut self, cost: f64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
 
-    let mut messages = vec![];
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+
+        if self.tokens < cost {
+            let wait = (cost - self.tokens) / self.rate;
+            thread::sleep(Duration::from_secs_f64(wait));
+            self.tokens += wait * self.rate;
+        }
+
+        self.tokens -= cost;
+    }
+}
+
+// A message split into the parts that can be precomputed per thread and the
+// timestamp that has to be stamped at send time. For `raw` the tail is empty
+// and the head already holds the whole line.
+struct Message {
+    head: String,
+    tail: String,
+}
+
+// Structured-data elements drawn at random for RFC 5424 so receivers exercise
+// their SD parser. The enterprise number 32473 is the one reserved for examples.
+const SD_ELEMENTS: &[&str] = &[
+    "[exampleSDID@32473 iut=\"3\" eventSource=\"app\" eventID=\"1011\"]",
+    "[origin@32473 software=\"syslog-hv\" swVersion=\"0.1.0\"]",
+    "[meta@32473 sequenceId=\"7\"]",
+    "[timeQuality@32473 tzKnown=\"1\" isSynced=\"1\"]",
+];
+const MSGIDS: &[&str] = &["-", "TCPIN", "TCPOUT", "ID47", "PING"];
+
+// An open connection to the target. UDP stays connectionless and framing-free;
+// the stream transports carry one RFC 6587 framed message per write.
+enum Conn {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(native_tls::TlsStream<TcpStream>),
+}
+
+// Wrap a message in the selected RFC 6587 framing. Octet-counting prepends the
+// decimal byte length and a space, non-transparent framing appends a newline.
+fn frame(message: &str, framing: Framing) -> Vec<u8> {
+    match framing {
+        Framing::OctetCounting => format!("{} {}", message.len(), message),
+        Framing::Lf => format!("{message}\n"),
+    }
+    .into_bytes()
+}
+
+// Stamp the send-time timestamp onto a precomputed message and return the wire
+// line. `raw` has no timestamp so its head already is the whole line.
+fn render(msg: &Message, format: Format) -> String {
+    match format {
+        Format::Raw => msg.head.clone(),
+        Format::Rfc3164 => format!(
+            "{}{}{}",
+            msg.head,
+            Local::now().format("%b %e %H:%M:%S"),
+            msg.tail
+        ),
+        Format::Rfc5424 => format!(
+            "{}{}{}",
+            msg.head,
+            Local::now().to_rfc3339_opts(SecondsFormat::Micros, false),
+            msg.tail
+        ),
+    }
+}
+
+// Pre-encode the target into a sockaddr so every sendmmsg can point msg_name at
+// it without re-parsing the address each batch.
+#[cfg(target_os = "linux")]
+fn encode_sockaddr(target: &Target) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+
+    let len = match target.ip {
+        IpAddr::V4(addr) => {
+            let sin = &mut storage as *mut _ as *mut libc::sockaddr_in;
+            unsafe {
+                (*sin).sin_family = libc::AF_INET as libc::sa_family_t;
+                (*sin).sin_port = target.port.to_be();
+                (*sin).sin_addr.s_addr = u32::from_ne_bytes(addr.octets());
+            }
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        IpAddr::V6(addr) => {
+            let sin6 = &mut storage as *mut _ as *mut libc::sockaddr_in6;
+            unsafe {
+                (*sin6).sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                (*sin6).sin6_port = target.port.to_be();
+                (*sin6).sin6_addr.s6_addr = addr.octets();
+            }
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, len as libc::socklen_t)
+}
+
+// Flush a batch of datagrams with a single sendmmsg call, retrying the tail on a
+// short send. Returns (datagrams, bytes) actually handed to the kernel.
+#[cfg(target_os = "linux")]
+fn send_batch(
+    fd: libc::c_int,
+    addr: &libc::sockaddr_storage,
+    addrlen: libc::socklen_t,
+    bufs: &[Vec<u8>],
+) -> std::io::Result<(u64, u64)> {
+    let mut iovecs: Vec<libc::iovec> = bufs
+        .iter()
+        .map(|b| libc::iovec {
+            iov_base: b.as_ptr() as *mut libc::c_void,
+            iov_len: b.len(),
+        })
+        .collect();
+
+    let name = addr as *const _ as *mut libc::c_void;
+    let iov_ptr = iovecs.as_mut_ptr();
+
+    let mut msgs: Vec<libc::mmsghdr> = (0..bufs.len())
+        .map(|i| {
+            let mut hdr: libc::msghdr = unsafe { std::mem::zeroed() };
+            hdr.msg_name = name;
+            hdr.msg_namelen = addrlen;
+            hdr.msg_iov = unsafe { iov_ptr.add(i) };
+            hdr.msg_iovlen = 1;
+            libc::mmsghdr {
+                msg_hdr: hdr,
+                msg_len: 0,
+            }
+        })
+        .collect();
+
+    let total = bufs.len();
+    let mut sent = 0usize;
+
+    while sent < total {
+        let ret = unsafe {
+            libc::sendmmsg(
+                fd,
+                msgs.as_mut_ptr().add(sent),
+                (total - sent) as libc::c_uint,
+                0,
+            )
+        };
+
+        if ret < 0 {
+            let err = std::io::Error::last_os_error();
+            if sent == 0 {
+                return Err(err);
+            }
+            break;
+        }
+        if ret == 0 {
+            break;
+        }
+
+        sent += ret as usize;
+    }
+
+    let bytes = bufs[..sent].iter().map(|b| b.len() as u64).sum();
+    Ok((sent as u64, bytes))
+}
+
+// Write a batch of already-framed messages to a stream transport, counting what
+// landed before an error. A mid-batch error drops the unsent rest of the batch
+// and returns the partial counts; the broken stream then fails on the next
+// batch's first write, which is what triggers the reconnect.
+fn write_stream(w: &mut impl Write, bufs: &[Vec<u8>]) -> std::io::Result<(u64, u64)> {
+    let (mut packets, mut bytes) = (0u64, 0u64);
+
+    for buf in bufs {
+        match w.write_all(buf) {
+            Ok(()) => {
+                packets += 1;
+                bytes += buf.len() as u64;
+            }
+            Err(e) => {
+                if packets == 0 {
+                    return Err(e);
+                }
+                break;
+            }
+        }
+    }
+
+    Ok((packets, bytes))
+}
+
+// Non-Linux fallback for UDP batching: a plain loop of send_to.
+#[cfg(not(target_os = "linux"))]
+fn send_batch_udp(socket: &UdpSocket, target: &str, bufs: &[Vec<u8>]) -> std::io::Result<(u64, u64)> {
+    let (mut packets, mut bytes) = (0u64, 0u64);
+
+    for buf in bufs {
+        match socket.send_to(buf, target) {
+            Ok(n) => {
+                packets += 1;
+                bytes += n as u64;
+            }
+            Err(e) => {
+                if packets == 0 {
+                    return Err(e);
+                }
+                break;
+            }
+        }
+    }
+
+    Ok((packets, bytes))
+}
+
+fn connect_tls(addr: &str, domain: &str, insecure: bool) -> std::io::Result<Conn> {
+    let mut builder = native_tls::TlsConnector::builder();
+    if insecure {
+        builder
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true);
+    }
+    let connector = builder
+        .build()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    let tcp = TcpStream::connect(addr)?;
+    let tls = connector
+        .connect(domain, tcp)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    Ok(Conn::Tls(tls))
+}
+
+// Open a connection, retrying with a short backoff. High-volume TCP receivers
+// drop slow senders, so a reset is expected and must not panic the thread.
+fn connect(config: &Config) -> Conn {
+    let target = config.target.as_str();
+
+    loop {
+        let attempt = match config.transport {
+            Transport::Udp => UdpSocket::bind(config.target.bind_to()).map(Conn::Udp),
+            Transport::Tcp => TcpStream::connect(&target).map(Conn::Tcp),
+            Transport::Tls => {
+                connect_tls(&target, &config.target.ip.to_string(), config.tls_insecure)
+            }
+        };
+
+        match attempt {
+            Ok(conn) => return conn,
+            Err(e) => {
+                eprintln!("connect to {target} failed: {e}, retrying");
+                thread::sleep(Duration::from_millis(200));
+            }
+        }
+    }
+}
+
+// Precompute the 4096 static message bodies from the current knob values. The
+// pool is rebuilt whenever the control socket retunes priority/word counts.
+fn build_messages(
+    config: &Config,
+    rng: &mut impl Rng,
+    hostname: &str,
+    pid: u32,
+    words_max: u64,
+    words_fixed: u64,
+    priority_max: u64,
+) -> Vec<Message> {
+    let mut messages = Vec::with_capacity(4 * 1024);
 
     for _ in 0..(4 * 1024) {
-        let length = if config.options.words_fixed != 0 {
-            config.options.words_fixed
+        let length = if words_fixed != 0 {
+            words_fixed
         } else {
-            rng.gen_range(1..config.options.words_max)
+            rng.gen_range(1..words_max.max(2))
         };
 
         let words = rand_word::new(length as usize);
-        let prio = rng.gen_range(0..config.options.priority_max);
-        let message = format!("<{prio}> {words}");
+        let severity = rng.gen_range(0..priority_max.max(1));
+        let prio = config.options.facility as u16 * 8 + severity as u16;
+
+        let message = match config.format {
+            Format::Raw => Message {
+                head: format!("<{severity}> {words}"),
+                tail: String::new(),
+            },
+            Format::Rfc3164 => Message {
+                head: format!("<{prio}>"),
+                tail: format!(" {hostname} syslog-hv[{pid}]: {words}"),
+            },
+            Format::Rfc5424 => {
+                let sd = SD_ELEMENTS.choose(rng).unwrap();
+                let msgid = MSGIDS.choose(rng).unwrap();
+                Message {
+                    head: format!("<{prio}>1 "),
+                    tail: format!(" {hostname} syslog-hv {pid} {msgid} {sd} {words}"),
+                }
+            }
+        };
 
         messages.push(message);
     }
 
-    let mut tstats = Stats {
-        packets: 0,
-        bytes: 0,
+    messages
+}
+
+fn tx_thread(signalled: Arc<AtomicBool>, config: Arc<Config>, tstats: Arc<CachePadded<ThreadStats>>) {
+    let mut conn = connect(&config);
+
+    let mut rng = rand::thread_rng();
+
+    let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| String::from("localhost"));
+    let pid = std::process::id();
+
+    // Snapshot the message-shaping knobs and build the initial pool; the tuple
+    // lets us notice when the control socket retunes them and rebuild.
+    let mut knobs = (
+        config.options.words_max.load(Ordering::Relaxed),
+        config.options.words_fixed.load(Ordering::Relaxed),
+        config.options.priority_max.load(Ordering::Relaxed),
+    );
+    let mut messages = build_messages(&config, &mut rng, &hostname, pid, knobs.0, knobs.1, knobs.2);
+
+    let histogram = matches!(config.report, Report::Histogram);
+    let mut last_send = Instant::now();
+
+    let batch = config.options.batch.max(1);
+
+    // Split the aggregate target evenly across the tx threads. `bytes_mode`
+    // selects bit-rate pacing (cost = message bytes) over pps (cost = 1).
+    let threads = config.threads_nr.max(1) as f64;
+    let (mut limiter, bytes_mode) = if config.options.target_pps > 0.0 {
+        (
+            Some(RateLimiter::new(config.options.target_pps / threads)),
+            false,
+        )
+    } else if config.options.target_mbit > 0.0 {
+        let bytes_per_sec = config.options.target_mbit * 1_000_000.0 / 8.0 / threads;
+        (Some(RateLimiter::new(bytes_per_sec)), true)
+    } else {
+        (None, false)
     };
 
-    let mut tick = Instant::now();
+    #[cfg(target_os = "linux")]
+    let (sockaddr, socklen) = encode_sockaddr(&config.target);
 
     loop {
-        let idx = rng.gen_range(0..messages.len());
-        let message = &messages[idx];
+        if config.options.paused.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(100));
+            if signalled.load(Ordering::SeqCst) {
+                break;
+            }
+            continue;
+        }
+
+        // Rebuild the pool if the control socket retuned any shaping knob.
+        let current = (
+            config.options.words_max.load(Ordering::Relaxed),
+            config.options.words_fixed.load(Ordering::Relaxed),
+            config.options.priority_max.load(Ordering::Relaxed),
+        );
+        if current != knobs {
+            knobs = current;
+            messages = build_messages(&config, &mut rng, &hostname, pid, knobs.0, knobs.1, knobs.2);
+        }
+
+        // Fill up to `batch` datagrams, framing stream transports as we go.
+        let mut bufs = Vec::with_capacity(batch);
+
+        for _ in 0..batch {
+            let idx = rng.gen_range(0..messages.len());
+            let message = render(&messages[idx], config.format);
+
+            let buf = match config.transport {
+                Transport::Udp => message.into_bytes(),
+                Transport::Tcp | Transport::Tls => frame(&message, config.framing),
+            };
+
+            bufs.push(buf);
+        }
+
+        if let Some(limiter) = limiter.as_mut() {
+            let cost = if bytes_mode {
+                bufs.iter().map(|b| b.len() as f64).sum()
+            } else {
+                bufs.len() as f64
+            };
+            limiter.acquire(cost);
+        }
 
-        let bytes = socket
-            .send_to(message.as_bytes(), config.target.as_str())
-            .unwrap();
+        let result = match &mut conn {
+            Conn::Udp(socket) => {
+                #[cfg(target_os = "linux")]
+                {
+                    send_batch(socket.as_raw_fd(), &sockaddr, socklen, &bufs)
+                }
+                #[cfg(not(target_os = "linux"))]
+                {
+                    send_batch_udp(socket, &config.target.as_str(), &bufs)
+                }
+            }
+            Conn::Tcp(stream) => write_stream(stream, &bufs),
+            Conn::Tls(stream) => write_stream(stream, &bufs),
+        };
 
-        tstats.packets += 1;
-        tstats.bytes += bytes as u64;
+        let (packets, bytes) = match result {
+            Ok(counts) => counts,
+            Err(_) => {
+                thread::sleep(Duration::from_millis(200));
+                conn = connect(&config);
+                continue;
+            }
+        };
 
-        thread::sleep(Duration::from_micros(config.options.sleep_us));
+        tstats.packets.fetch_add(packets, Ordering::Relaxed);
+        tstats.bytes.fetch_add(bytes, Ordering::Relaxed);
 
-        if tick.elapsed() >= Duration::from_millis(100) {
-            tick = Instant::now();
+        if histogram {
+            let now = Instant::now();
+            tstats.record_interval(now.duration_since(last_send).as_micros() as u64);
+            last_send = now;
+        }
+
+        // The token bucket handles pacing when a target rate is set; otherwise
+        // fall back to the crude fixed sleep.
+        if limiter.is_none() {
+            thread::sleep(Duration::from_micros(
+                config.options.sleep_us.load(Ordering::Relaxed),
+            ));
+        }
+
+        if signalled.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+}
 
-            let mut stats = stats.lock().unwrap();
+// Handle one newline-delimited command from the control socket and return the
+// JSON reply. Unknown or malformed commands report an error rather than panic.
+fn control_command(line: &str, config: &Config, stats: &[Arc<CachePadded<ThreadStats>>]) -> String {
+    let mut parts = line.split_whitespace();
 
-            stats.packets += tstats.packets;
-            stats.bytes += tstats.bytes;
+    match parts.next() {
+        Some("stats") => {
+            let packets: u64 = stats.iter().map(|s| s.packets.load(Ordering::Relaxed)).sum();
+            let bytes: u64 = stats.iter().map(|s| s.bytes.load(Ordering::Relaxed)).sum();
+            format!("{{\"packets\":{packets},\"bytes\":{bytes}}}")
+        }
+        Some("pause") => {
+            config.options.paused.store(true, Ordering::Relaxed);
+            String::from("{\"ok\":true,\"paused\":true}")
+        }
+        Some("resume") => {
+            config.options.paused.store(false, Ordering::Relaxed);
+            String::from("{\"ok\":true,\"paused\":false}")
+        }
+        Some("set") => {
+            let knob = parts.next();
+            let value = parts.next().and_then(|v| v.parse::<u64>().ok());
+
+            match (knob, value) {
+                (Some("sleep-us"), Some(v)) => {
+                    config.options.sleep_us.store(v, Ordering::Relaxed);
+                    format!("{{\"ok\":true,\"sleep-us\":{v}}}")
+                }
+                (Some("words-max"), Some(v)) => {
+                    config.options.words_max.store(v, Ordering::Relaxed);
+                    format!("{{\"ok\":true,\"words-max\":{v}}}")
+                }
+                (Some("words-fixed"), Some(v)) => {
+                    config.options.words_fixed.store(v, Ordering::Relaxed);
+                    format!("{{\"ok\":true,\"words-fixed\":{v}}}")
+                }
+                (Some("priority-max"), Some(v)) => {
+                    config.options.priority_max.store(v, Ordering::Relaxed);
+                    format!("{{\"ok\":true,\"priority-max\":{v}}}")
+                }
+                _ => String::from("{\"ok\":false,\"error\":\"bad set command\"}"),
+            }
+        }
+        _ => String::from("{\"ok\":false,\"error\":\"unknown command\"}"),
+    }
+}
 
-            tstats.packets = 0;
-            tstats.bytes = 0;
+// Bind a local TCP listener and serve newline-delimited control commands so the
+// load can be ramped during a long soak test without restarting.
+fn control_thread(addr: String, signalled: Arc<AtomicBool>, config: Arc<Config>, stats: AllStats) {
+    let listener = match TcpListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("control socket bind to {addr} failed: {e}");
+            return;
         }
+    };
 
+    for stream in listener.incoming() {
         if signalled.load(Ordering::SeqCst) {
             break;
         }
+
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+
+        let reader = BufReader::new(match stream.try_clone() {
+            Ok(s) => s,
+            Err(_) => continue,
+        });
+
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            if line.trim() == "quit" {
+                break;
+            }
+
+            let reply = control_command(line.trim(), &config, stats.as_slice());
+            if writeln!(stream, "{reply}").is_err() {
+                break;
+            }
+        }
     }
 }
 
@@ -181,19 +854,25 @@ fn main() {
             ip: args.target_ip,
             port: args.target_port,
         },
+        transport: args.transport,
+        framing: args.framing,
+        format: args.format,
+        report: args.report,
+        tls_insecure: args.tls_insecure,
+        threads_nr: args.threads_nr,
         options: Options {
-            sleep_us: args.sleep_us,
-            priority_max: args.priority_max,
-            words_max: args.words_max,
-            words_fixed: args.words_fixed,
+            sleep_us: AtomicU64::new(args.sleep_us),
+            priority_max: AtomicU64::new(args.priority_max as u64),
+            facility: args.facility,
+            words_max: AtomicU64::new(args.words_max as u64),
+            words_fixed: AtomicU64::new(args.words_fixed as u64),
+            paused: AtomicBool::new(false),
+            batch: args.batch,
+            target_pps: args.target_pps,
+            target_mbit: args.target_mbit,
         },
     };
 
-    let stats = Stats {
-        packets: 0,
-        bytes: 0,
-    };
-
     let signalled = Arc::new(AtomicBool::new(false));
     let sig = Arc::clone(&signalled);
 
@@ -203,37 +882,91 @@ fn main() {
     .expect("ctrl+c setup error");
 
     let config = Arc::new(config);
-    let stats = Arc::new(Mutex::new(stats));
 
-    let mut handles = vec![];
+    // One cache-padded counter block per tx thread; the reporter sums them.
+    let stats: AllStats = Arc::new(
+        (0..args.threads_nr)
+            .map(|_| Arc::new(CachePadded::new(ThreadStats::new())))
+            .collect(),
+    );
 
-    for _ in 0..args.threads_nr {
+    if let Some(addr) = args.control_addr.clone() {
         let signalled = Arc::clone(&signalled);
         let config = Arc::clone(&config);
         let stats = Arc::clone(&stats);
 
+        thread::spawn(move || {
+            control_thread(addr, signalled, config, stats);
+        });
+    }
+
+    let mut handles = vec![];
+
+    for tstats in stats.iter() {
+        let signalled = Arc::clone(&signalled);
+        let config = Arc::clone(&config);
+        let tstats = Arc::clone(tstats);
+
         let handle = thread::spawn(move || {
-            tx_thread(signalled, config, stats);
+            tx_thread(signalled, config, tstats);
         });
 
         handles.push(handle);
     }
 
+    // Cumulative totals from the previous tick, so each second prints a delta.
+    let mut prev_packets = 0u64;
+    let mut prev_bytes = 0u64;
+    let mut prev_buckets = [0u64; HIST_BUCKETS];
+
     loop {
         thread::sleep(Duration::from_secs(1));
 
-        {
-            let mut stats = stats.lock().unwrap();
-
-            println!(
-                "{:.1} k pps, {:.1} Mbit/s -> {}",
-                stats.packets as f64 / 1000_f64,
-                (stats.bytes * 8) as f64 / (1024 * 1024) as f64,
-                config.target.as_str(),
-            );
-
-            stats.packets = 0;
-            stats.bytes = 0;
+        let packets: u64 = stats.iter().map(|s| s.packets.load(Ordering::Relaxed)).sum();
+        let bytes: u64 = stats.iter().map(|s| s.bytes.load(Ordering::Relaxed)).sum();
+
+        let d_packets = packets - prev_packets;
+        let d_bytes = bytes - prev_bytes;
+        prev_packets = packets;
+        prev_bytes = bytes;
+
+        let pps = d_packets as f64 / 1000_f64;
+        let mbit = (d_bytes * 8) as f64 / 1_000_000.0;
+        let target = config.target.as_str();
+
+        match config.report {
+            Report::Line => {
+                println!("{pps:.1} k pps, {mbit:.1} Mbit/s -> {target}");
+            }
+            Report::Json => {
+                println!(
+                    "{{\"pps\":{d_packets},\"mbit\":{mbit:.1},\"target\":\"{target}\"}}"
+                );
+            }
+            Report::Histogram => {
+                // Roll up the per-thread interval buckets into a per-second delta.
+                let mut buckets = [0u64; HIST_BUCKETS];
+                for s in stats.iter() {
+                    for (b, cell) in buckets.iter_mut().zip(s.buckets.iter()) {
+                        *b += cell.load(Ordering::Relaxed);
+                    }
+                }
+
+                let mut delta = [0u64; HIST_BUCKETS];
+                for ((d, &now), &prev) in delta
+                    .iter_mut()
+                    .zip(buckets.iter())
+                    .zip(prev_buckets.iter())
+                {
+                    *d = now - prev;
+                }
+                prev_buckets = buckets;
+
+                let (min, p50, p99) = percentiles(&delta);
+                println!(
+                    "{pps:.1} k pps, {mbit:.1} Mbit/s, send-interval us min/p50/p99 {min}/{p50}/{p99} -> {target}"
+                );
+            }
         }
 
         if signalled.load(Ordering::SeqCst) {